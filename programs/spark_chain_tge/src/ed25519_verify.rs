@@ -1,5 +1,14 @@
+use anchor_lang::solana_program::ed25519_program;
+use anchor_lang::solana_program::instruction::Instruction;
+use anchor_lang::solana_program::program_error::ProgramError;
 use anchor_lang::solana_program::pubkey::Pubkey;
 
+// Ed25519 precompile instruction layout
+const SIGNATURE_OFFSETS_START: usize = 2;
+const SIGNATURE_OFFSETS_SERIALIZED_SIZE: usize = 14;
+const PUBKEY_SERIALIZED_SIZE: usize = 32;
+const SIGNATURE_SERIALIZED_SIZE: usize = 64;
+
 /// Verify an Ed25519 signature using the ed25519-dalek crate
 pub fn verify_signature(
     pubkey: &Pubkey,
@@ -23,12 +32,187 @@ pub fn verify_signature(
     }
 }
 
+/// Verify that the instruction immediately preceding `current_index` is an Ed25519
+/// precompile instruction over exactly this pubkey, signature and message
+pub fn verify_adjacent_ed25519_instruction<F>(
+    current_index: usize,
+    load_instruction: F,
+    pubkey: &Pubkey,
+    signature: &[u8; 64],
+    message: &[u8],
+) -> anyhow::Result<bool>
+where
+    F: Fn(usize) -> Result<Instruction, ProgramError>,
+{
+    if current_index == 0 {
+        return Ok(false);
+    }
+
+    let ix = load_instruction(current_index - 1)
+        .map_err(|e| anyhow::anyhow!("Failed to load instruction: {}", e))?;
+
+    Ok(ed25519_instruction_matches(&ix, pubkey, signature, message))
+}
+
+/// Check a single Ed25519 precompile instruction carries the expected signature.
+/// All offsets must point into the instruction's own data.
+fn ed25519_instruction_matches(
+    ix: &Instruction,
+    pubkey: &Pubkey,
+    signature: &[u8; 64],
+    message: &[u8],
+) -> bool {
+    if ix.program_id != ed25519_program::ID || !ix.accounts.is_empty() {
+        return false;
+    }
+
+    let data = &ix.data;
+    if data.len() < SIGNATURE_OFFSETS_START + SIGNATURE_OFFSETS_SERIALIZED_SIZE || data[0] != 1 {
+        return false;
+    }
+
+    let read_u16 = |at: usize| u16::from_le_bytes([data[at], data[at + 1]]);
+    let offsets = SIGNATURE_OFFSETS_START;
+    let signature_offset = read_u16(offsets) as usize;
+    let signature_ix_index = read_u16(offsets + 2);
+    let pubkey_offset = read_u16(offsets + 4) as usize;
+    let pubkey_ix_index = read_u16(offsets + 6);
+    let message_offset = read_u16(offsets + 8) as usize;
+    let message_size = read_u16(offsets + 10) as usize;
+    let message_ix_index = read_u16(offsets + 12);
+
+    if signature_ix_index != u16::MAX || pubkey_ix_index != u16::MAX || message_ix_index != u16::MAX
+    {
+        return false;
+    }
+
+    let slice = |offset: usize, len: usize| data.get(offset..offset.checked_add(len)?);
+    slice(signature_offset, SIGNATURE_SERIALIZED_SIZE) == Some(&signature[..])
+        && slice(pubkey_offset, PUBKEY_SERIALIZED_SIZE) == Some(&pubkey.to_bytes()[..])
+        && message_size == message.len()
+        && slice(message_offset, message_size) == Some(message)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use ed25519_dalek::{Keypair, Signer};
     use rand::rngs::OsRng;
 
+    // Build an Ed25519 precompile instruction with all data inline
+    fn ed25519_instruction(pubkey: &Pubkey, signature: &[u8; 64], message: &[u8]) -> Instruction {
+        let data_start = (SIGNATURE_OFFSETS_START + SIGNATURE_OFFSETS_SERIALIZED_SIZE) as u16;
+        let pubkey_offset = data_start;
+        let signature_offset = pubkey_offset + PUBKEY_SERIALIZED_SIZE as u16;
+        let message_offset = signature_offset + SIGNATURE_SERIALIZED_SIZE as u16;
+
+        let mut data = vec![1u8, 0u8];
+        for value in [
+            signature_offset,
+            u16::MAX,
+            pubkey_offset,
+            u16::MAX,
+            message_offset,
+            message.len() as u16,
+            u16::MAX,
+        ] {
+            data.extend_from_slice(&value.to_le_bytes());
+        }
+        data.extend_from_slice(&pubkey.to_bytes());
+        data.extend_from_slice(signature);
+        data.extend_from_slice(message);
+
+        Instruction {
+            program_id: ed25519_program::ID,
+            accounts: vec![],
+            data,
+        }
+    }
+
+    fn other_instruction() -> Instruction {
+        Instruction {
+            program_id: Pubkey::new_unique(),
+            accounts: vec![],
+            data: vec![],
+        }
+    }
+
+    #[test]
+    fn test_adjacent_ed25519_instruction_accepted() -> anyhow::Result<()> {
+        let pubkey = Pubkey::new_unique();
+        let signature = [7u8; 64];
+        let message = b"proof message";
+
+        // [ed25519, commit] with commit at index 1
+        let instructions = [
+            ed25519_instruction(&pubkey, &signature, message),
+            other_instruction(),
+        ];
+        let load = |i: usize| {
+            instructions
+                .get(i)
+                .cloned()
+                .ok_or(ProgramError::InvalidArgument)
+        };
+
+        assert!(verify_adjacent_ed25519_instruction(
+            1, load, &pubkey, &signature, message
+        )?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_non_adjacent_ed25519_instruction_rejected() -> anyhow::Result<()> {
+        let pubkey = Pubkey::new_unique();
+        let signature = [7u8; 64];
+        let message = b"proof message";
+
+        // [ed25519, unrelated, commit] with commit at index 2
+        let instructions = [
+            ed25519_instruction(&pubkey, &signature, message),
+            other_instruction(),
+            other_instruction(),
+        ];
+        let load = |i: usize| {
+            instructions
+                .get(i)
+                .cloned()
+                .ok_or(ProgramError::InvalidArgument)
+        };
+
+        assert!(!verify_adjacent_ed25519_instruction(
+            2, load, &pubkey, &signature, message
+        )?);
+
+        // Commit as the first instruction has nothing preceding it
+        assert!(!verify_adjacent_ed25519_instruction(
+            0, load, &pubkey, &signature, message
+        )?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_adjacent_ed25519_instruction_wrong_message_rejected() -> anyhow::Result<()> {
+        let pubkey = Pubkey::new_unique();
+        let signature = [7u8; 64];
+
+        let instructions = [
+            ed25519_instruction(&pubkey, &signature, b"other"),
+            other_instruction(),
+        ];
+        let load = |i: usize| {
+            instructions
+                .get(i)
+                .cloned()
+                .ok_or(ProgramError::InvalidArgument)
+        };
+
+        assert!(!verify_adjacent_ed25519_instruction(
+            1, load, &pubkey, &signature, b"proof"
+        )?);
+        Ok(())
+    }
+
     #[test]
     fn test_verify() -> anyhow::Result<()> {
         let mut csprng = OsRng;
@@ -0,0 +1,166 @@
+use anchor_lang::prelude::{pubkey, Pubkey};
+
+// Pyth pull oracle price account, owned by the Pyth Solana receiver program.
+// PriceUpdateV2 layout (Borsh, little-endian):
+// [0..8)    Anchor discriminator
+// [8..40)   write_authority (Pubkey)
+// [40..)    verification_level: 0 = Partial { num_signatures: u8 }, 1 = Full
+// then PriceFeedMessage:
+//           feed_id [u8; 32], price i64, conf u64, exponent i32, publish_time i64,
+//           prev_publish_time i64, ema_price i64, ema_conf u64
+// then      posted_slot u64
+pub const PYTH_RECEIVER_PROGRAM_ID: Pubkey = pubkey!("rec5EKMGg6MxZYaMdyBfgwp4d5rB9T1VQH5pJv5LtFJ");
+pub const PRICE_UPDATE_V2_DISCRIMINATOR: [u8; 8] = [34, 241, 35, 99, 157, 126, 244, 205];
+
+const VERIFICATION_LEVEL_OFFSET: usize = 40;
+const VERIFICATION_FULL: u8 = 1;
+const FEED_ID_LEN: usize = 32;
+// price, conf, exponent, publish_time
+const PRICE_FIELDS_LEN: usize = 8 + 8 + 4 + 8;
+const MAX_EXPO_MAGNITUDE: i32 = 18;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct OraclePrice {
+    pub price: i64,
+    pub expo: i32,
+    pub publish_time: i64,
+}
+
+impl OraclePrice {
+    /// Older than max_staleness seconds. A publish time slightly ahead of the cluster
+    /// clock counts as fresh, as in the Pyth SDK.
+    pub fn is_stale(&self, now: i64, max_staleness: i64) -> bool {
+        self.publish_time.saturating_add(max_staleness) < now
+    }
+}
+
+/// Parse a fully verified Pyth PriceUpdateV2 account and reject nonsensical prices.
+/// Staleness is left to the caller.
+pub fn read_price(data: &[u8]) -> anyhow::Result<OraclePrice> {
+    if data.len() <= VERIFICATION_LEVEL_OFFSET || data[..8] != PRICE_UPDATE_V2_DISCRIMINATOR {
+        anyhow::bail!("Not a price update account");
+    }
+    // Partially verified updates carry fewer guardian signatures than Pyth requires
+    if data[VERIFICATION_LEVEL_OFFSET] != VERIFICATION_FULL {
+        anyhow::bail!("Price update is not fully verified");
+    }
+
+    let price_offset = VERIFICATION_LEVEL_OFFSET + 1 + FEED_ID_LEN;
+    let fields = data
+        .get(price_offset..price_offset + PRICE_FIELDS_LEN)
+        .ok_or_else(|| anyhow::anyhow!("Price update account too small: {} bytes", data.len()))?;
+    let price = i64::from_le_bytes(fields[0..8].try_into()?);
+    let expo = i32::from_le_bytes(fields[16..20].try_into()?);
+    let publish_time = i64::from_le_bytes(fields[20..28].try_into()?);
+
+    if price <= 0 {
+        anyhow::bail!("Non-positive price: {}", price);
+    }
+    if expo.abs() > MAX_EXPO_MAGNITUDE {
+        anyhow::bail!("Exponent out of range: {}", expo);
+    }
+
+    Ok(OraclePrice {
+        price,
+        expo,
+        publish_time,
+    })
+}
+
+/// Convert a USD-denominated point cost into lamports at the oracle SOL/USD price.
+/// usd_rate is USD per point scaled by `precision`:
+/// lamports = points * usd_rate * 10^9 / (precision * price * 10^expo)
+pub fn required_lamports(
+    points: u64,
+    usd_rate: u64,
+    precision: u64,
+    price: &OraclePrice,
+) -> Option<u64> {
+    let mut numerator = (points as u128)
+        .checked_mul(usd_rate as u128)?
+        .checked_mul(1_000_000_000)?;
+    let mut denominator = (precision as u128).checked_mul(price.price as u128)?;
+
+    let scale = 10u128.checked_pow(price.expo.unsigned_abs())?;
+    if price.expo < 0 {
+        numerator = numerator.checked_mul(scale)?;
+    } else {
+        denominator = denominator.checked_mul(scale)?;
+    }
+
+    u64::try_from(numerator / denominator).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A fully verified PriceUpdateV2 as posted by the Pyth receiver
+    fn mock_feed(price: i64, expo: i32, publish_time: i64) -> Vec<u8> {
+        let mut data = PRICE_UPDATE_V2_DISCRIMINATOR.to_vec();
+        data.extend_from_slice(&[0u8; 32]); // write_authority
+        data.push(VERIFICATION_FULL);
+        data.extend_from_slice(&[0u8; 32]); // feed_id
+        data.extend_from_slice(&price.to_le_bytes());
+        data.extend_from_slice(&0u64.to_le_bytes()); // conf
+        data.extend_from_slice(&expo.to_le_bytes());
+        data.extend_from_slice(&publish_time.to_le_bytes());
+        data.extend_from_slice(&[0u8; 8 + 8 + 8 + 8]); // prev_publish_time, ema, posted_slot
+        data
+    }
+
+    #[test]
+    fn test_read_fresh_price() -> anyhow::Result<()> {
+        // $150.00000000 per SOL, published 10s ago
+        let data = mock_feed(15_000_000_000, -8, 990);
+        let price = read_price(&data)?;
+        assert_eq!(price.price, 15_000_000_000);
+        assert_eq!(price.expo, -8);
+        assert_eq!(price.publish_time, 990);
+        assert!(!price.is_stale(1_000, 60));
+        // Slightly ahead of the cluster clock is still fresh
+        assert!(!price.is_stale(980, 60));
+        assert!(price.is_stale(1_051, 60));
+        Ok(())
+    }
+
+    #[test]
+    fn test_reject_invalid_price() {
+        assert!(read_price(&mock_feed(0, -8, 1_000)).is_err());
+        assert!(read_price(&mock_feed(-1, -8, 1_000)).is_err());
+        assert!(read_price(&mock_feed(1, -40, 1_000)).is_err());
+        assert!(read_price(&mock_feed(15_000_000_000, -8, 1_000)[..80]).is_err());
+        assert!(read_price(&[0u8; 10]).is_err());
+
+        // Wrong account type
+        let mut data = mock_feed(15_000_000_000, -8, 1_000);
+        data[0] ^= 1;
+        assert!(read_price(&data).is_err());
+
+        // Partially verified: Partial { num_signatures } shifts the message by a byte
+        let mut data = mock_feed(15_000_000_000, -8, 1_000);
+        data[VERIFICATION_LEVEL_OFFSET] = 0;
+        data.insert(VERIFICATION_LEVEL_OFFSET + 1, 5);
+        assert!(read_price(&data).is_err());
+    }
+
+    #[test]
+    fn test_required_lamports_from_usd_rate() -> anyhow::Result<()> {
+        let price = read_price(&mock_feed(15_000_000_000, -8, 1_000))?;
+
+        // $0.15 per point (scaled by 10^9), 100 points = $15 = 0.1 SOL at $150
+        let lamports = required_lamports(100, 150_000_000, 1_000_000_000, &price);
+        assert_eq!(lamports, Some(100_000_000));
+
+        // Price doubles -> half the lamports
+        let price = read_price(&mock_feed(30_000_000_000, -8, 1_000))?;
+        let lamports = required_lamports(100, 150_000_000, 1_000_000_000, &price);
+        assert_eq!(lamports, Some(50_000_000));
+
+        // Positive exponent: $300 expressed as 3 * 10^2
+        let price = read_price(&mock_feed(3, 2, 1_000))?;
+        let lamports = required_lamports(100, 150_000_000, 1_000_000_000, &price);
+        assert_eq!(lamports, Some(50_000_000));
+        Ok(())
+    }
+}
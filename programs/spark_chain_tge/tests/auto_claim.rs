@@ -0,0 +1,81 @@
+mod common;
+
+use anchor_spl::associated_token::get_associated_token_address;
+use common::*;
+use solana_signer::Signer;
+
+// The commit that fills the target pays the committer's allocation out in the same
+// instruction, and only into the committer's own ATA
+#[tokio::test]
+async fn test_commit_auto_claims_into_committer_ata() {
+    let mut context = program_test().start_with_context().await;
+    let launch = start_launch(&mut context, LaunchConfig::default()).await;
+    let user = new_user(&mut context, &launch).await;
+    let other = new_user(&mut context, &launch).await;
+
+    let commit = Commit {
+        points: 2 * LAMPORTS_PER_SOL,
+        sol_amount: 2 * LAMPORTS_PER_SOL,
+        nonce: 1,
+        auto_claim: true,
+    };
+
+    let user_ata = get_associated_token_address(&user.pubkey(), &launch.mint);
+
+    // Pointing the payout at someone else's token account is rejected
+    let mut redirected = launch.commit_ix(&user.pubkey(), &commit);
+    let payout = redirected
+        .accounts
+        .iter_mut()
+        .find(|meta| meta.pubkey == user_ata)
+        .unwrap();
+    payout.pubkey = get_associated_token_address(&other.pubkey(), &launch.mint);
+    assert!(process(&mut context, &[redirected], &[&user]).await.is_err());
+
+    let commit_ix = launch.commit_ix(&user.pubkey(), &commit);
+    process(&mut context, &[commit_ix], &[&user]).await.unwrap();
+
+    assert_eq!(token_balance(&mut context, &user_ata).await, 1_000_000_000);
+    assert_eq!(token_balance(&mut context, &token_vault()).await, 0);
+    let commitment: spark_chain_tge::UserCommitment =
+        fetch(&mut context, &user_commitment(&user.pubkey())).await;
+    assert!(commitment.tokens_claimed);
+    let state: spark_chain_tge::DistributionState =
+        fetch(&mut context, &distribution_state()).await;
+    assert!(state.finalized);
+    assert_eq!(state.total_tokens_claimed, 1_000_000_000);
+}
+
+// With a soft cap the filled sale stays Active until finalize_outcome, so the filling
+// commit goes through and the claim waits
+#[tokio::test]
+async fn test_commit_skips_auto_claim_until_outcome_decided() {
+    let mut context = program_test().start_with_context().await;
+    let launch = start_launch(&mut context, LaunchConfig::default()).await;
+    let set_soft_cap = launch.set_soft_cap_ix(LAMPORTS_PER_SOL, 0);
+    process(&mut context, &[set_soft_cap], &[&launch.authority])
+        .await
+        .unwrap();
+    let user = new_user(&mut context, &launch).await;
+
+    let commit = Commit {
+        points: 2 * LAMPORTS_PER_SOL,
+        sol_amount: 2 * LAMPORTS_PER_SOL,
+        nonce: 1,
+        auto_claim: true,
+    };
+    let commit_ix = launch.commit_ix(&user.pubkey(), &commit);
+    process(&mut context, &[commit_ix], &[&user]).await.unwrap();
+
+    let user_ata = get_associated_token_address(&user.pubkey(), &launch.mint);
+    assert_eq!(token_balance(&mut context, &user_ata).await, 0);
+    assert_eq!(token_balance(&mut context, &token_vault()).await, 1_000_000_000);
+    let commitment: spark_chain_tge::UserCommitment =
+        fetch(&mut context, &user_commitment(&user.pubkey())).await;
+    assert!(!commitment.tokens_claimed);
+    assert_eq!(commitment.sol_amount, 2 * LAMPORTS_PER_SOL);
+    let state: spark_chain_tge::DistributionState =
+        fetch(&mut context, &distribution_state()).await;
+    assert!(!state.finalized);
+    assert_eq!(state.status, spark_chain_tge::LaunchStatus::Active);
+}
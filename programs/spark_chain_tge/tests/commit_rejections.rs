@@ -0,0 +1,88 @@
+mod common;
+
+use anchor_lang::solana_program::instruction::Instruction;
+use common::*;
+use solana_keypair::Keypair;
+use solana_signer::Signer;
+
+const COMMIT: Commit = Commit {
+    points: LAMPORTS_PER_SOL,
+    sol_amount: LAMPORTS_PER_SOL,
+    nonce: 1,
+    auto_claim: false,
+};
+
+// Carries a backend signature over different points, so it only gets past checks
+// that run before the proof is verified
+fn forged_commit_ix(launch: &Launch, user: &Keypair) -> Instruction {
+    let signed = Commit {
+        points: COMMIT.points + 1,
+        ..COMMIT
+    };
+    let expiry = launch.commit_start_time + 60 * 60;
+    let signature = launch.sign_proof(&user.pubkey(), &signed, expiry);
+    launch.commit_ix_with_signature(&user.pubkey(), &COMMIT, signature)
+}
+
+// While the sale is open, the forged proof is what fails
+#[tokio::test]
+async fn test_open_sale_rejects_forged_proof() {
+    let mut context = program_test().start_with_context().await;
+    let launch = start_launch(&mut context, LaunchConfig::default()).await;
+    let user = new_user(&mut context, &launch).await;
+
+    let forged_ix = forged_commit_ix(&launch, &user);
+    assert_eq!(
+        process(&mut context, &[forged_ix], &[&user])
+            .await
+            .map_err(|e| e.unwrap()),
+        Err(program_error(
+            spark_chain_tge::ErrorCode::Ed25519VerificationFailed
+        ))
+    );
+}
+
+// A commit after the window closes is rejected before any signature work
+#[tokio::test]
+async fn test_ended_window_rejects_commit_before_proof() {
+    let mut context = program_test().start_with_context().await;
+    let launch = start_launch(&mut context, LaunchConfig::default()).await;
+    let user = new_user(&mut context, &launch).await;
+    let state: spark_chain_tge::DistributionState =
+        fetch(&mut context, &distribution_state()).await;
+    warp_to_timestamp(&mut context, state.commit_end_time + 1).await;
+
+    let forged_ix = forged_commit_ix(&launch, &user);
+    assert_eq!(
+        process(&mut context, &[forged_ix], &[&user])
+            .await
+            .map_err(|e| e.unwrap()),
+        Err(program_error(spark_chain_tge::ErrorCode::CommitPeriodEnded))
+    );
+}
+
+// Filling the target stops commits, and later ones are rejected before any signature work
+#[tokio::test]
+async fn test_filled_target_rejects_commit_before_proof() {
+    let mut context = program_test().start_with_context().await;
+    let launch = start_launch(&mut context, LaunchConfig::default()).await;
+    let filler = new_user(&mut context, &launch).await;
+    let fill = Commit {
+        points: 2 * LAMPORTS_PER_SOL,
+        sol_amount: 2 * LAMPORTS_PER_SOL,
+        ..COMMIT
+    };
+    let fill_ix = launch.commit_ix(&filler.pubkey(), &fill);
+    process(&mut context, &[fill_ix], &[&filler]).await.unwrap();
+
+    let user = new_user(&mut context, &launch).await;
+    let forged_ix = forged_commit_ix(&launch, &user);
+    assert_eq!(
+        process(&mut context, &[forged_ix], &[&user])
+            .await
+            .map_err(|e| e.unwrap()),
+        Err(program_error(
+            spark_chain_tge::ErrorCode::DistributionNotActive
+        ))
+    );
+}
@@ -0,0 +1,127 @@
+mod common;
+
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::instruction::Instruction;
+use anchor_lang::{InstructionData, ToAccountMetas};
+use anchor_spl::associated_token::get_associated_token_address;
+use anchor_spl::token::spl_token;
+use common::*;
+use solana_keypair::Keypair;
+use solana_signer::Signer;
+use spark_chain_tge::PaymentConfig;
+
+const USDC_AMOUNT: u64 = 1_000_000_000;
+
+fn set_payment_configs_ix(launch: &Launch, usdc: &Pubkey) -> Instruction {
+    let mut payment_configs = [PaymentConfig {
+        mint: Pubkey::default(),
+        rate: 0,
+    }; 4];
+    // Lamports and USDC base units both count one common unit each
+    payment_configs[0].rate = PRECISION_FACTOR;
+    payment_configs[1] = PaymentConfig {
+        mint: *usdc,
+        rate: PRECISION_FACTOR,
+    };
+    Instruction {
+        program_id: spark_chain_tge::ID,
+        accounts: spark_chain_tge::accounts::UpdateDistributionState {
+            distribution_state: distribution_state(),
+            authority: launch.authority.pubkey(),
+        }
+        .to_account_metas(None),
+        data: spark_chain_tge::instruction::SetPaymentConfigs {
+            payment_configs,
+            target_raise_common_unit: 10 * USDC_AMOUNT,
+        }
+        .data(),
+    }
+}
+
+fn set_lifetime_raise_cap_ix(launch: &Launch, lifetime_raise_cap: u64) -> Instruction {
+    Instruction {
+        program_id: spark_chain_tge::ID,
+        accounts: spark_chain_tge::accounts::UpdateBackendAuthority {
+            backend_authority: backend_authority(),
+            authority: launch.authority.pubkey(),
+        }
+        .to_account_metas(None),
+        data: spark_chain_tge::instruction::SetLifetimeRaiseCap { lifetime_raise_cap }.data(),
+    }
+}
+
+fn commit_with_token_ix(launch: &Launch, user: &Keypair, usdc: &Pubkey) -> Instruction {
+    let commit = Commit {
+        points: USDC_AMOUNT,
+        sol_amount: USDC_AMOUNT,
+        nonce: 1,
+        auto_claim: false,
+    };
+    let expiry = launch.commit_start_time + 60 * 60;
+    Instruction {
+        program_id: spark_chain_tge::ID,
+        accounts: spark_chain_tge::accounts::CommitWithToken {
+            user_commitment: user_commitment(&user.pubkey()),
+            backend_authority: backend_authority(),
+            distribution_state: distribution_state(),
+            user: user.pubkey(),
+            user_payment_account: get_associated_token_address(&user.pubkey(), usdc),
+            payment_vault: get_associated_token_address(&distribution_state(), usdc),
+            token_program: spl_token::ID,
+            system_program: anchor_lang::system_program::ID,
+            instructions_sysvar: None,
+            fee_treasury_account: None,
+        }
+        .to_account_metas(None),
+        data: spark_chain_tge::instruction::CommitWithToken {
+            points: commit.points,
+            amount: commit.sol_amount,
+            backend_signature: launch.sign_proof(&user.pubkey(), &commit, expiry),
+            nonce: commit.nonce,
+            expiry,
+            proof_version: PROOF_VERSION,
+            vesting_schedule_id: 0,
+            pow_nonce: 0,
+            reputation_multiplier_bps: DEFAULT_REPUTATION_BPS,
+            user_cap: 0,
+        }
+        .data(),
+    }
+}
+
+// Token commits count toward the program-wide lifetime cap in common units
+#[tokio::test]
+async fn test_token_commits_count_toward_lifetime_cap() {
+    let mut context = program_test().start_with_context().await;
+    let launch = start_launch(&mut context, LaunchConfig::default()).await;
+    let usdc = create_mint(&mut context, 6).await;
+    create_funded_ata(&mut context, &usdc, &distribution_state(), 0).await;
+    let configure = [
+        set_payment_configs_ix(&launch, &usdc),
+        set_lifetime_raise_cap_ix(&launch, USDC_AMOUNT * 3 / 2),
+    ];
+    process(&mut context, &configure, &[&launch.authority])
+        .await
+        .unwrap();
+
+    let first = new_user(&mut context, &launch).await;
+    create_funded_ata(&mut context, &usdc, &first.pubkey(), USDC_AMOUNT).await;
+    let commit_ix = commit_with_token_ix(&launch, &first, &usdc);
+    process(&mut context, &[commit_ix], &[&first]).await.unwrap();
+    let backend: spark_chain_tge::BackendAuthority =
+        fetch(&mut context, &backend_authority()).await;
+    assert_eq!(backend.lifetime_raised, USDC_AMOUNT);
+
+    // A second commit of the same size would take the total past the cap
+    let second = new_user(&mut context, &launch).await;
+    create_funded_ata(&mut context, &usdc, &second.pubkey(), USDC_AMOUNT).await;
+    let commit_ix = commit_with_token_ix(&launch, &second, &usdc);
+    assert_eq!(
+        process(&mut context, &[commit_ix], &[&second])
+            .await
+            .map_err(|e| e.unwrap()),
+        Err(program_error(
+            spark_chain_tge::ErrorCode::LifetimeRaiseCapExceeded
+        ))
+    );
+}
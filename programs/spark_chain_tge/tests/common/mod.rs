@@ -0,0 +1,477 @@
+// Shared setup for the program tests: a bank running the program natively, the token
+// accounts a launch needs and backend-signed commit proofs.
+#![allow(dead_code)]
+
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::{
+    entrypoint::MAX_PERMITTED_DATA_INCREASE,
+    instruction::{Instruction, InstructionError},
+    program_pack::Pack,
+    sysvar,
+};
+use anchor_lang::system_program;
+use anchor_lang::{InstructionData, ToAccountMetas};
+use anchor_spl::associated_token::{self, get_associated_token_address};
+use anchor_spl::token::spl_token;
+use ed25519_dalek::{Keypair as BackendKeypair, PublicKey, SecretKey, Signer as _};
+use solana_program_test::{processor, BanksClientError, ProgramTest, ProgramTestContext};
+use solana_keypair::Keypair;
+use solana_signer::Signer;
+use solana_transaction::Transaction;
+use solana_transaction_error::TransactionError;
+
+pub const PRECISION_FACTOR: u64 = 1_000_000_000;
+pub const LAMPORTS_PER_SOL: u64 = 1_000_000_000;
+pub const ANNOUNCEMENT_WINDOW: i64 = 24 * 60 * 60;
+pub const PROOF_VERSION: u8 = 7;
+pub const DEFAULT_REPUTATION_BPS: u16 = 10_000;
+
+// Anchor's entry wants the accounts to outlive the call, which the processor! shim
+// can't express, so the program runs on leaked 'static copies that are written back
+// once it returns.
+fn process_instruction(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: &[u8],
+) -> anchor_lang::solana_program::entrypoint::ProgramResult {
+    let mut copies: Vec<AccountInfo<'static>> = Vec::with_capacity(accounts.len());
+    for account in accounts {
+        // Duplicate metas share one account, as they do in the runtime's input
+        let copy = match copies.iter().find(|copy| copy.key == account.key) {
+            Some(first) => first.clone(),
+            None => leak_account(account),
+        };
+        copies.push(copy);
+    }
+    spark_chain_tge::entry(program_id, leak_accounts(copies.clone()), data)?;
+
+    for (account, copy) in accounts.iter().zip(&copies) {
+        if !account.is_writable {
+            continue;
+        }
+        **account.try_borrow_mut_lamports()? = copy.lamports();
+        if copy.data_len() != account.data_len() {
+            account.resize(copy.data_len())?;
+        }
+        account.try_borrow_mut_data()?.copy_from_slice(&copy.try_borrow_data()?);
+        if account.owner != copy.owner {
+            account.assign(copy.owner);
+        }
+    }
+    Ok(())
+}
+
+// AccountInfo::resize reads the original length from the four bytes before the key
+// and writes the new one into the eight before the data, as laid out in the runtime's
+// serialized input; system program CPIs resize the accounts they create.
+#[repr(C)]
+struct LeakedKey {
+    padding: u32,
+    original_data_len: u32,
+    key: Pubkey,
+}
+
+fn leak_account(account: &AccountInfo) -> AccountInfo<'static> {
+    let data = account.data.borrow();
+    let key = Box::leak(Box::new(LeakedKey {
+        padding: 0,
+        original_data_len: data.len() as u32,
+        key: *account.key,
+    }));
+
+    let buffer = vec![0u8; 8 + 8 + data.len() + MAX_PERMITTED_DATA_INCREASE].leak();
+    let aligned = buffer.as_ptr().align_offset(8);
+    let (length, rest) = buffer[aligned..].split_at_mut(8);
+    length.copy_from_slice(&(data.len() as u64).to_le_bytes());
+    let (leaked_data, _headroom) = rest.split_at_mut(data.len());
+    leaked_data.copy_from_slice(&data);
+
+    AccountInfo::new(
+        &key.key,
+        account.is_signer,
+        account.is_writable,
+        Box::leak(Box::new(account.lamports())),
+        leaked_data,
+        Box::leak(Box::new(*account.owner)),
+        account.executable,
+        account.rent_epoch,
+    )
+}
+
+fn leak_accounts(accounts: Vec<AccountInfo<'static>>) -> &'static [AccountInfo<'static>] {
+    Box::leak(accounts.into_boxed_slice())
+}
+
+pub fn program_test() -> ProgramTest {
+    ProgramTest::new(
+        "spark_chain_tge",
+        spark_chain_tge::ID,
+        processor!(process_instruction),
+    )
+}
+
+pub fn distribution_state() -> Pubkey {
+    Pubkey::find_program_address(&[b"global_distribution_state"], &spark_chain_tge::ID).0
+}
+
+pub fn token_vault() -> Pubkey {
+    Pubkey::find_program_address(
+        &[b"token_vault", distribution_state().as_ref()],
+        &spark_chain_tge::ID,
+    )
+    .0
+}
+
+pub fn backend_authority() -> Pubkey {
+    Pubkey::find_program_address(&[b"backend_authority"], &spark_chain_tge::ID).0
+}
+
+pub fn user_commitment(user: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[b"commitment", user.as_ref()], &spark_chain_tge::ID).0
+}
+
+pub fn label<const N: usize>(text: &str) -> [u8; N] {
+    let mut label = [0u8; N];
+    label[..text.len()].copy_from_slice(text.as_bytes());
+    label
+}
+
+pub async fn process(
+    context: &mut ProgramTestContext,
+    instructions: &[Instruction],
+    signers: &[&Keypair],
+) -> std::result::Result<(), BanksClientError> {
+    let blockhash = context.get_new_latest_blockhash().await.unwrap();
+    let mut all_signers = vec![&context.payer];
+    all_signers.extend_from_slice(signers);
+    let transaction = Transaction::new_signed_with_payer(
+        instructions,
+        Some(&context.payer.pubkey()),
+        &all_signers,
+        blockhash,
+    );
+    context.banks_client.process_transaction(transaction).await
+}
+
+// What a single-instruction transaction failing with the program's error reports
+pub fn program_error(error: spark_chain_tge::ErrorCode) -> TransactionError {
+    TransactionError::InstructionError(0, InstructionError::Custom(error.into()))
+}
+
+pub async fn now(context: &mut ProgramTestContext) -> i64 {
+    context
+        .banks_client
+        .get_sysvar::<Clock>()
+        .await
+        .unwrap()
+        .unix_timestamp
+}
+
+pub async fn warp_to_timestamp(context: &mut ProgramTestContext, unix_timestamp: i64) {
+    let mut clock = context.banks_client.get_sysvar::<Clock>().await.unwrap();
+    clock.unix_timestamp = unix_timestamp;
+    context.set_sysvar(&clock);
+}
+
+pub async fn fund(context: &mut ProgramTestContext, to: &Pubkey, lamports: u64) {
+    let transfer = anchor_lang::solana_program::system_instruction::transfer(
+        &context.payer.pubkey(),
+        to,
+        lamports,
+    );
+    process(context, &[transfer], &[]).await.unwrap();
+}
+
+pub async fn create_mint(context: &mut ProgramTestContext, decimals: u8) -> Pubkey {
+    let mint = Keypair::new();
+    let rent = context.banks_client.get_rent().await.unwrap();
+    let payer = context.payer.pubkey();
+    let instructions = [
+        anchor_lang::solana_program::system_instruction::create_account(
+            &payer,
+            &mint.pubkey(),
+            rent.minimum_balance(spl_token::state::Mint::LEN),
+            spl_token::state::Mint::LEN as u64,
+            &spl_token::ID,
+        ),
+        spl_token::instruction::initialize_mint2(&spl_token::ID, &mint.pubkey(), &payer, None, decimals)
+            .unwrap(),
+    ];
+    process(context, &instructions, &[&mint]).await.unwrap();
+    mint.pubkey()
+}
+
+// Creates owner's ATA for mint and mints amount into it
+pub async fn create_funded_ata(
+    context: &mut ProgramTestContext,
+    mint: &Pubkey,
+    owner: &Pubkey,
+    amount: u64,
+) -> Pubkey {
+    let payer = context.payer.pubkey();
+    let ata = get_associated_token_address(owner, mint);
+    let mut instructions = vec![
+        associated_token::spl_associated_token_account::instruction::create_associated_token_account(
+            &payer,
+            owner,
+            mint,
+            &spl_token::ID,
+        ),
+    ];
+    if amount > 0 {
+        instructions.push(
+            spl_token::instruction::mint_to(&spl_token::ID, mint, &ata, &payer, &[], amount)
+                .unwrap(),
+        );
+    }
+    process(context, &instructions, &[]).await.unwrap();
+    ata
+}
+
+pub async fn token_balance(context: &mut ProgramTestContext, account: &Pubkey) -> u64 {
+    let account = context
+        .banks_client
+        .get_account(*account)
+        .await
+        .unwrap()
+        .expect("token account exists");
+    spl_token::state::Account::unpack(&account.data).unwrap().amount
+}
+
+pub async fn fetch<T: AccountDeserialize>(context: &mut ProgramTestContext, address: &Pubkey) -> T {
+    let account = context
+        .banks_client
+        .get_account(*address)
+        .await
+        .unwrap()
+        .expect("account exists");
+    T::try_deserialize(&mut account.data.as_slice()).unwrap()
+}
+
+pub struct Launch {
+    pub authority: Keypair,
+    pub mint: Pubkey,
+    pub backend: BackendKeypair,
+    pub commit_start_time: i64,
+    pub price_oracle: Option<Pubkey>,
+}
+
+pub struct LaunchConfig {
+    pub rate: u64,
+    pub target_raise_sol: u64,
+    pub token_pool: u64,
+}
+
+impl Default for LaunchConfig {
+    fn default() -> Self {
+        Self {
+            rate: PRECISION_FACTOR, // 1 lamport per point
+            target_raise_sol: 2 * LAMPORTS_PER_SOL,
+            token_pool: 1_000_000_000,
+        }
+    }
+}
+
+pub fn setup_distribution_ix(
+    authority: &Pubkey,
+    mint: &Pubkey,
+    config: &LaunchConfig,
+    commit_start_time: i64,
+) -> Instruction {
+    Instruction {
+        program_id: spark_chain_tge::ID,
+        accounts: spark_chain_tge::accounts::SetupDistribution {
+            distribution_state: distribution_state(),
+            token_vault: token_vault(),
+            token_mint: *mint,
+            authority_token_account: get_associated_token_address(authority, mint),
+            authority: *authority,
+            token_program: spl_token::ID,
+            system_program: system_program::ID,
+            rent: sysvar::rent::ID,
+        }
+        .to_account_metas(None),
+        data: spark_chain_tge::instruction::SetupDistribution {
+            commit_end_time: commit_start_time + 7 * 24 * 60 * 60,
+            rate: config.rate,
+            target_raise_sol: config.target_raise_sol,
+            max_extension_time: 0,
+            target_token_pool: config.token_pool,
+            commit_start_time,
+            name: label("Spark"),
+            symbol: label("SPK"),
+            fund_amount: config.token_pool,
+        }
+        .data(),
+    }
+}
+
+// A funded launch with its backend authority, warped to the start of the commit period
+pub async fn start_launch(context: &mut ProgramTestContext, config: LaunchConfig) -> Launch {
+    let authority = Keypair::new();
+    fund(context, &authority.pubkey(), 10 * LAMPORTS_PER_SOL).await;
+    let mint = create_mint(context, 9).await;
+    create_funded_ata(context, &mint, &authority.pubkey(), config.token_pool).await;
+
+    let secret = SecretKey::from_bytes(&[7u8; 32]).unwrap();
+    let backend = BackendKeypair {
+        public: PublicKey::from(&secret),
+        secret,
+    };
+
+    let commit_start_time = now(context).await + ANNOUNCEMENT_WINDOW;
+    let setup = setup_distribution_ix(&authority.pubkey(), &mint, &config, commit_start_time);
+    let init_backend = Instruction {
+        program_id: spark_chain_tge::ID,
+        accounts: spark_chain_tge::accounts::InitializeBackendAuthority {
+            backend_authority: backend_authority(),
+            authority: authority.pubkey(),
+            system_program: system_program::ID,
+        }
+        .to_account_metas(None),
+        data: spark_chain_tge::instruction::InitializeBackendAuthority {
+            backend_pubkey: Pubkey::new_from_array(backend.public.to_bytes()),
+        }
+        .data(),
+    };
+    process(context, &[setup, init_backend], &[&authority])
+        .await
+        .unwrap();
+    warp_to_timestamp(context, commit_start_time).await;
+
+    Launch {
+        authority,
+        mint,
+        backend,
+        commit_start_time,
+        price_oracle: None,
+    }
+}
+
+pub struct Commit {
+    pub points: u64,
+    pub sol_amount: u64,
+    pub nonce: u64,
+    pub auto_claim: bool,
+}
+
+impl Launch {
+    // The backend's version 7 proof message, mirroring create_proof_message
+    pub fn sign_proof(&self, user: &Pubkey, commit: &Commit, expiry: i64) -> [u8; 64] {
+        let mut message = Vec::new();
+        message.extend_from_slice(b"POINTS_DEDUCTION_PROOF:");
+        message.push(PROOF_VERSION);
+        message.extend_from_slice(user.as_ref());
+        message.extend_from_slice(&commit.points.to_le_bytes());
+        message.extend_from_slice(&commit.nonce.to_le_bytes());
+        message.extend_from_slice(&expiry.to_le_bytes());
+        message.push(commit.auto_claim as u8);
+        message.push(0); // vesting schedule
+        message.extend_from_slice(spark_chain_tge::ID.as_ref());
+        message.push(0); // no session key
+        message.extend_from_slice(&DEFAULT_REPUTATION_BPS.to_le_bytes());
+        message.extend_from_slice(&0u64.to_le_bytes()); // no user cap
+        message.push(0); // KYC level
+        self.backend.sign(&message).to_bytes()
+    }
+
+    pub fn set_soft_cap_ix(&self, soft_cap_sol: u64, min_committers: u32) -> Instruction {
+        Instruction {
+            program_id: spark_chain_tge::ID,
+            accounts: spark_chain_tge::accounts::UpdateDistributionState {
+                distribution_state: distribution_state(),
+                authority: self.authority.pubkey(),
+            }
+            .to_account_metas(None),
+            data: spark_chain_tge::instruction::SetSoftCap {
+                soft_cap_sol,
+                min_committers,
+            }
+            .data(),
+        }
+    }
+
+    pub fn set_price_oracle_ix(
+        &self,
+        price_oracle: Option<Pubkey>,
+        max_staleness: i64,
+    ) -> Instruction {
+        Instruction {
+            program_id: spark_chain_tge::ID,
+            accounts: spark_chain_tge::accounts::UpdateDistributionState {
+                distribution_state: distribution_state(),
+                authority: self.authority.pubkey(),
+            }
+            .to_account_metas(None),
+            data: spark_chain_tge::instruction::SetPriceOracle {
+                price_oracle,
+                max_staleness,
+            }
+            .data(),
+        }
+    }
+
+    pub fn commit_ix(&self, user: &Pubkey, commit: &Commit) -> Instruction {
+        let expiry = self.commit_start_time + 60 * 60;
+        self.commit_ix_with_signature(user, commit, self.sign_proof(user, commit, expiry))
+    }
+
+    pub fn commit_ix_with_signature(
+        &self,
+        user: &Pubkey,
+        commit: &Commit,
+        backend_signature: [u8; 64],
+    ) -> Instruction {
+        let expiry = self.commit_start_time + 60 * 60;
+        let (token_vault, user_token_account, token_program) = if commit.auto_claim {
+            (
+                Some(token_vault()),
+                Some(get_associated_token_address(user, &self.mint)),
+                Some(spl_token::ID),
+            )
+        } else {
+            (None, None, None)
+        };
+        Instruction {
+            program_id: spark_chain_tge::ID,
+            accounts: spark_chain_tge::accounts::CommitResources {
+                user_commitment: user_commitment(user),
+                backend_authority: backend_authority(),
+                distribution_state: distribution_state(),
+                user: *user,
+                system_program: system_program::ID,
+                token_vault,
+                user_token_account,
+                token_program,
+                instructions_sysvar: None,
+                price_oracle: self.price_oracle,
+                session_owner: None,
+            }
+            .to_account_metas(None),
+            data: spark_chain_tge::instruction::CommitResources {
+                points: commit.points,
+                sol_amount: commit.sol_amount,
+                backend_signature,
+                nonce: commit.nonce,
+                expiry,
+                auto_claim: commit.auto_claim,
+                proof_version: PROOF_VERSION,
+                vesting_schedule_id: 0,
+                pow_nonce: 0,
+                memo: None,
+                reputation_multiplier_bps: DEFAULT_REPUTATION_BPS,
+                user_cap: 0,
+                kyc_level: 0,
+            }
+            .data(),
+        }
+    }
+}
+
+// A funded wallet with an empty ATA for the launch's token
+pub async fn new_user(context: &mut ProgramTestContext, launch: &Launch) -> Keypair {
+    let user = Keypair::new();
+    fund(context, &user.pubkey(), 10 * LAMPORTS_PER_SOL).await;
+    create_funded_ata(context, &launch.mint, &user.pubkey(), 0).await;
+    user
+}
@@ -0,0 +1,101 @@
+mod common;
+
+use anchor_lang::prelude::*;
+use common::*;
+use solana_account::Account;
+use solana_signer::Signer;
+
+const PYTH_RECEIVER_PROGRAM_ID: Pubkey = pubkey!("rec5EKMGg6MxZYaMdyBfgwp4d5rB9T1VQH5pJv5LtFJ");
+const MAX_STALENESS: i64 = 60;
+
+// A fully verified Pyth PriceUpdateV2 account for SOL/USD
+fn mock_price_update(price: i64, expo: i32, publish_time: i64) -> Account {
+    let mut data = vec![34, 241, 35, 99, 157, 126, 244, 205]; // discriminator
+    data.extend_from_slice(&[0u8; 32]); // write_authority
+    data.push(1); // VerificationLevel::Full
+    data.extend_from_slice(&[0u8; 32]); // feed_id
+    data.extend_from_slice(&price.to_le_bytes());
+    data.extend_from_slice(&0u64.to_le_bytes()); // conf
+    data.extend_from_slice(&expo.to_le_bytes());
+    data.extend_from_slice(&publish_time.to_le_bytes());
+    data.extend_from_slice(&[0u8; 8 + 8 + 8 + 8]); // prev_publish_time, ema, posted_slot
+    Account {
+        lamports: LAMPORTS_PER_SOL,
+        data,
+        owner: PYTH_RECEIVER_PROGRAM_ID,
+        executable: false,
+        rent_epoch: 0,
+    }
+}
+
+// $0.15 per point at $150 per SOL prices 1,000 points at exactly 1 SOL
+#[tokio::test]
+async fn test_commit_priced_by_oracle_and_rejected_when_stale() {
+    let mut context = program_test().start_with_context().await;
+    let mut launch = start_launch(
+        &mut context,
+        LaunchConfig {
+            rate: 150_000_000,
+            target_raise_sol: 10 * LAMPORTS_PER_SOL,
+            ..LaunchConfig::default()
+        },
+    )
+    .await;
+    let price_oracle = Pubkey::new_unique();
+    let published = now(&mut context).await;
+    context.set_account(
+        &price_oracle,
+        &mock_price_update(15_000_000_000, -8, published).into(),
+    );
+    let set_oracle = launch.set_price_oracle_ix(Some(price_oracle), MAX_STALENESS);
+    process(&mut context, &[set_oracle], &[&launch.authority])
+        .await
+        .unwrap();
+    launch.price_oracle = Some(price_oracle);
+    let user = new_user(&mut context, &launch).await;
+
+    // Under the oracle price
+    let underpaid = Commit {
+        points: 1_000,
+        sol_amount: LAMPORTS_PER_SOL - 1,
+        nonce: 1,
+        auto_claim: false,
+    };
+    let commit_ix = launch.commit_ix(&user.pubkey(), &underpaid);
+    assert!(process(&mut context, &[commit_ix], &[&user]).await.is_err());
+
+    let commit = Commit {
+        points: 1_000,
+        sol_amount: LAMPORTS_PER_SOL,
+        nonce: 1,
+        auto_claim: false,
+    };
+    let commit_ix = launch.commit_ix(&user.pubkey(), &commit);
+    process(&mut context, &[commit_ix], &[&user]).await.unwrap();
+    let commitment: spark_chain_tge::UserCommitment =
+        fetch(&mut context, &user_commitment(&user.pubkey())).await;
+    assert_eq!(commitment.sol_amount, LAMPORTS_PER_SOL);
+
+    // The feed stops publishing
+    warp_to_timestamp(&mut context, published + MAX_STALENESS + 1).await;
+    let stale = Commit { nonce: 2, ..commit };
+    let commit_ix = launch.commit_ix(&user.pubkey(), &stale);
+    assert_eq!(
+        process(&mut context, &[commit_ix], &[&user])
+            .await
+            .map_err(|e| e.unwrap()),
+        Err(program_error(spark_chain_tge::ErrorCode::OracleStale))
+    );
+
+    // A feed account not owned by the Pyth receiver is not a price
+    let mut forged = mock_price_update(15_000_000_000, -8, published + MAX_STALENESS);
+    forged.owner = Pubkey::new_unique();
+    context.set_account(&price_oracle, &forged.into());
+    let forged_ix = launch.commit_ix(&user.pubkey(), &stale);
+    assert_eq!(
+        process(&mut context, &[forged_ix], &[&user])
+            .await
+            .map_err(|e| e.unwrap()),
+        Err(program_error(spark_chain_tge::ErrorCode::InvalidOracle))
+    );
+}